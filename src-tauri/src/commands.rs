@@ -1,91 +1,377 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::sync::Semaphore;
+
+use crate::error::LinkFetchError;
+use crate::image_cache::{self, ImageCache};
+use crate::metadata_cache::MetadataCache;
+
+/// Maximum number of links `fetch_link_metadata_batch` resolves concurrently.
+const BATCH_CONCURRENCY: usize = 8;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Retries apply to network/timeout failures only.
+const MAX_RETRIES: u32 = 2;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LinkMetadata {
     title: Option<String>,
     description: Option<String>,
     image: Option<String>,
     site_name: Option<String>,
+    favicon: Option<String>,
     url: String,
 }
 
+/// Subset of the oEmbed response spec (<https://oembed.com>).
+#[derive(Deserialize)]
+struct OEmbedResponse {
+    title: Option<String>,
+    thumbnail_url: Option<String>,
+    author_name: Option<String>,
+}
+
 #[tauri::command]
 pub fn is_dev() -> bool {
     cfg!(debug_assertions)
 }
 
 #[tauri::command]
-pub async fn fetch_link_metadata(url: String) -> Result<LinkMetadata, String> {
+pub async fn fetch_link_metadata(
+    image_cache: State<'_, ImageCache>,
+    metadata_cache: State<'_, MetadataCache>,
+    url: String,
+) -> Result<LinkMetadata, LinkFetchError> {
+    resolve_link_metadata(&image_cache, &metadata_cache, url).await
+}
+
+/// Invalidates the cached entry for `url` so the next
+/// `fetch_link_metadata` call re-hits the network instead of serving stale
+/// metadata.
+#[tauri::command]
+pub async fn invalidate_link_metadata(
+    metadata_cache: State<'_, MetadataCache>,
+    url: String,
+) -> Result<(), String> {
+    metadata_cache.invalidate(&url).await;
+    Ok(())
+}
+
+/// Resolves metadata for a batch of links in parallel, bounded to
+/// `BATCH_CONCURRENCY` in-flight requests at a time.
+#[tauri::command]
+pub async fn fetch_link_metadata_batch(
+    image_cache: State<'_, ImageCache>,
+    metadata_cache: State<'_, MetadataCache>,
+    urls: Vec<String>,
+) -> Result<Vec<Result<LinkMetadata, LinkFetchError>>, String> {
+    let semaphore = Semaphore::new(BATCH_CONCURRENCY);
+
+    let results = futures::future::join_all(urls.into_iter().map(|url| {
+        let semaphore = &semaphore;
+        let image_cache = &image_cache;
+        let metadata_cache = &metadata_cache;
+        async move {
+            let _permit = semaphore.acquire().await.expect("semaphore not closed");
+            resolve_link_metadata(image_cache, metadata_cache, url).await
+        }
+    }))
+    .await;
+
+    Ok(results)
+}
+
+/// Checks the metadata cache for a fresh entry before scraping `url`,
+/// storing whatever is freshly fetched back into the cache.
+async fn resolve_link_metadata(
+    image_cache: &ImageCache,
+    metadata_cache: &MetadataCache,
+    url: String,
+) -> Result<LinkMetadata, LinkFetchError> {
+    if let Some(cached) = metadata_cache.get_fresh(&url).await {
+        return Ok(cached);
+    }
+
+    // Always key by the URL the caller asked for, not `metadata.url` (the
+    // post-redirect final URL): otherwise a redirecting link never hits the
+    // cache, since `get_fresh` above looked it up under the pre-redirect URL.
+    let metadata = scrape_link_metadata(image_cache, url.clone()).await?;
+    metadata_cache.put(&url, metadata.clone()).await;
+    Ok(metadata)
+}
+
+fn should_retry(attempt: u32) -> bool {
+    attempt < MAX_RETRIES
+}
+
+fn next_backoff(current: Duration) -> Duration {
+    current * 2
+}
+
+// Retries network/timeout failures with exponential backoff.
+async fn send_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<reqwest::Response, LinkFetchError> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 0;
+
+    loop {
+        let result = client
+            .get(url)
+            .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,*/*;q=0.8")
+            .header("Accept-Language", "en-US,en;q=0.5")
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => return Ok(response),
+            Err(e) => {
+                let classified = if e.is_timeout() {
+                    LinkFetchError::Timeout
+                } else {
+                    LinkFetchError::Network
+                };
+
+                if !should_retry(attempt) {
+                    return Err(classified);
+                }
+
+                attempt += 1;
+                tokio::time::sleep(backoff).await;
+                backoff = next_backoff(backoff);
+            }
+        }
+    }
+}
+
+// A connection failure never reaches a response, so it carries no URL.
+struct FetchBodyError {
+    final_url: Option<reqwest::Url>,
+    kind: LinkFetchError,
+}
+
+impl From<LinkFetchError> for FetchBodyError {
+    fn from(kind: LinkFetchError) -> Self {
+        Self { final_url: None, kind }
+    }
+}
+
+/// Fetches `url`, returning its final (post-redirect) URL and body text.
+async fn fetch_html_body(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(reqwest::Url, String), FetchBodyError> {
+    let response = send_with_retry(client, url).await?;
+    let final_url = response.url().clone();
+
+    if !response.status().is_success() {
+        return Err(FetchBodyError {
+            final_url: Some(final_url),
+            kind: LinkFetchError::HttpStatus(response.status().as_u16()),
+        });
+    }
+
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("html"));
+
+    if !is_html {
+        return Err(FetchBodyError {
+            final_url: Some(final_url),
+            kind: LinkFetchError::NotHtml,
+        });
+    }
+
+    let html = response.text().await.map_err(|_| FetchBodyError {
+        final_url: Some(final_url.clone()),
+        kind: LinkFetchError::ParseFailed,
+    })?;
+    Ok((final_url, html))
+}
+
+/// Decodes HTML entities (`&amp;`, `&#39;`, ...) in scraped text.
+fn decode_entities(value: &str) -> String {
+    html_escape::decode_html_entities(value).into_owned()
+}
+
+/// Resolves a relative or protocol-relative URL against the page's final URL.
+fn resolve_url(base: &reqwest::Url, value: &str) -> String {
+    base.join(value).map(|u| u.to_string()).unwrap_or_else(|_| value.to_string())
+}
+
+async fn scrape_link_metadata(
+    image_cache: &ImageCache,
+    url: String,
+) -> Result<LinkMetadata, LinkFetchError> {
     // Create a client with browser-like headers to avoid being blocked
     let client = reqwest::Client::builder()
         .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+        .timeout(REQUEST_TIMEOUT)
         .build()
-        .map_err(|e| e.to_string())?;
+        .map_err(|_| LinkFetchError::Network)?;
 
-    // Fetch the HTML with proper headers
-    let response = client
-        .get(&url)
-        .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,*/*;q=0.8")
-        .header("Accept-Language", "en-US,en;q=0.5")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let html = response.text().await.map_err(|e| e.to_string())?;
+    let (final_url, html) = match fetch_html_body(&client, &url).await {
+        Ok(fetched) => fetched,
+        Err(FetchBodyError { final_url, kind: LinkFetchError::NotHtml }) => {
+            return Ok(LinkMetadata {
+                title: None,
+                description: None,
+                image: None,
+                site_name: None,
+                favicon: None,
+                url: final_url.map(|u| u.to_string()).unwrap_or(url),
+            })
+        }
+        Err(e) => return Err(e.kind),
+    };
 
     // Parse the HTML to extract Open Graph tags
     let document = scraper::Html::parse_document(&html);
 
-    // Selectors for Open Graph tags
+    // Selectors for Open Graph tags, with Twitter Card equivalents as a fallback
     let og_title_selector = scraper::Selector::parse("meta[property='og:title']").unwrap();
     let og_desc_selector = scraper::Selector::parse("meta[property='og:description']").unwrap();
     let og_image_selector = scraper::Selector::parse("meta[property='og:image']").unwrap();
     let og_site_name_selector = scraper::Selector::parse("meta[property='og:site_name']").unwrap();
+    let twitter_title_selector = scraper::Selector::parse("meta[name='twitter:title']").unwrap();
+    let twitter_desc_selector = scraper::Selector::parse("meta[name='twitter:description']").unwrap();
+    let twitter_image_selector = scraper::Selector::parse("meta[name='twitter:image']").unwrap();
     let title_selector = scraper::Selector::parse("title").unwrap();
     let meta_desc_selector = scraper::Selector::parse("meta[name='description']").unwrap();
+    let oembed_selector =
+        scraper::Selector::parse("link[type='application/json+oembed']").unwrap();
+    let favicon_selector = scraper::Selector::parse("link[rel~='icon']").unwrap();
 
-    let title = document
-        .select(&og_title_selector)
-        .next()
-        .and_then(|el| el.value().attr("content"))
-        .map(|s| s.to_string())
+    let meta_content = |selector: &scraper::Selector| {
+        document
+            .select(selector)
+            .next()
+            .and_then(|el| el.value().attr("content"))
+            .map(decode_entities)
+    };
+
+    let mut title = meta_content(&og_title_selector)
+        .or_else(|| meta_content(&twitter_title_selector))
         .or_else(|| {
             document
                 .select(&title_selector)
                 .next()
-                .map(|el| el.text().collect::<String>())
+                .map(|el| decode_entities(&el.text().collect::<String>()))
         });
 
-    let description = document
-        .select(&og_desc_selector)
-        .next()
-        .and_then(|el| el.value().attr("content"))
-        .map(|s| s.to_string())
+    let description = meta_content(&og_desc_selector)
+        .or_else(|| meta_content(&twitter_desc_selector))
         .or_else(|| {
             document
                 .select(&meta_desc_selector)
                 .next()
                 .and_then(|el| el.value().attr("content"))
-                .map(|s| s.to_string())
+                .map(decode_entities)
         });
 
-    let image = document
-        .select(&og_image_selector)
-        .next()
-        .and_then(|el| el.value().attr("content"))
-        .map(|s| s.to_string());
+    let mut image = meta_content(&og_image_selector).or_else(|| meta_content(&twitter_image_selector));
+
+    let mut site_name = meta_content(&og_site_name_selector);
+
+    // og:*/twitter:* already cover the full card; skip the extra round trip.
+    let needs_oembed = title.is_none() || image.is_none() || site_name.is_none();
+
+    if needs_oembed {
+        if let Some(oembed_url) = document
+            .select(&oembed_selector)
+            .next()
+            .and_then(|el| el.value().attr("href"))
+            .map(|href| resolve_url(&final_url, &decode_entities(href)))
+        {
+            if let Ok(oembed) = fetch_oembed(&client, &oembed_url).await {
+                title = title.or(oembed.title);
+                image = image.or(oembed.thumbnail_url);
+                site_name = site_name.or(oembed.author_name);
+            }
+        }
+    }
 
-    let site_name = document
-        .select(&og_site_name_selector)
+    let favicon = document
+        .select(&favicon_selector)
         .next()
-        .and_then(|el| el.value().attr("content"))
-        .map(|s| s.to_string());
+        .and_then(|el| el.value().attr("href"))
+        .map(|href| resolve_url(&final_url, &decode_entities(href)));
+
+    let image = match image.map(|remote_image| resolve_url(&final_url, &remote_image)) {
+        Some(remote_image) => image_cache::cache_remote_image(&client, image_cache, &remote_image)
+            .await
+            .ok(),
+        None => None,
+    };
 
     Ok(LinkMetadata {
         title,
         description,
         image,
         site_name,
-        url,
+        favicon,
+        url: final_url.to_string(),
     })
-}
\ No newline at end of file
+}
+
+async fn fetch_oembed(client: &reqwest::Client, oembed_url: &str) -> Result<OEmbedResponse, ()> {
+    client
+        .get(oembed_url)
+        .send()
+        .await
+        .map_err(|_| ())?
+        .json::<OEmbedResponse>()
+        .await
+        .map_err(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_up_to_max_then_stops() {
+        assert!(should_retry(0));
+        assert!(should_retry(MAX_RETRIES - 1));
+        assert!(!should_retry(MAX_RETRIES));
+    }
+
+    #[test]
+    fn backoff_doubles_each_attempt() {
+        let second = next_backoff(INITIAL_BACKOFF);
+        let third = next_backoff(second);
+        assert_eq!(second, INITIAL_BACKOFF * 2);
+        assert_eq!(third, INITIAL_BACKOFF * 4);
+    }
+
+    #[test]
+    fn decode_entities_handles_common_entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(decode_entities("it&#39;s"), "it's");
+        assert_eq!(decode_entities("&quot;quoted&quot;"), "\"quoted\"");
+    }
+
+    #[test]
+    fn resolve_url_handles_relative_and_scheme_relative_paths() {
+        let base = reqwest::Url::parse("https://example.com/articles/post").unwrap();
+        assert_eq!(
+            resolve_url(&base, "/img/card.png"),
+            "https://example.com/img/card.png"
+        );
+        assert_eq!(
+            resolve_url(&base, "//cdn.example/x.jpg"),
+            "https://cdn.example/x.jpg"
+        );
+        assert_eq!(
+            resolve_url(&base, "https://other.example/abs.png"),
+            "https://other.example/abs.png"
+        );
+    }
+}
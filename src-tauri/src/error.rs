@@ -0,0 +1,26 @@
+use serde::Serialize;
+
+/// Why a link preview could not be resolved.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum LinkFetchError {
+    Timeout,
+    HttpStatus(u16),
+    NotHtml,
+    Network,
+    ParseFailed,
+}
+
+impl std::fmt::Display for LinkFetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkFetchError::Timeout => write!(f, "request timed out"),
+            LinkFetchError::HttpStatus(status) => write!(f, "unexpected HTTP status {status}"),
+            LinkFetchError::NotHtml => write!(f, "response was not HTML"),
+            LinkFetchError::Network => write!(f, "network error"),
+            LinkFetchError::ParseFailed => write!(f, "failed to parse response body"),
+        }
+    }
+}
+
+impl std::error::Error for LinkFetchError {}
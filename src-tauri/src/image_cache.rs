@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// Content-addressed on-disk cache for images discovered while resolving
+/// link previews, served back to the webview under the `ogimg://` scheme.
+pub struct ImageCache {
+    dir: PathBuf,
+}
+
+impl ImageCache {
+    pub fn new(dir: PathBuf) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Derives the cache key for a remote image from its source URL and the
+    /// bytes actually fetched, so re-downloads of unchanged content land on
+    /// the same key while a changed image gets a fresh one.
+    pub fn key_for(url: &str, bytes: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        hasher.update(bytes);
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn bytes_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    fn mime_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.mime"))
+    }
+
+    /// Stores `bytes` under `key`, recording `mime` alongside it so the
+    /// protocol handler can answer with the original `Content-Type`.
+    pub fn store(&self, key: &str, bytes: &[u8], mime: &str) -> std::io::Result<()> {
+        std::fs::write(self.bytes_path(key), bytes)?;
+        std::fs::write(self.mime_path(key), mime)
+    }
+
+    /// Looks up a previously cached image, returning its bytes and mime type.
+    pub fn load(&self, key: &str) -> Option<(Vec<u8>, String)> {
+        let bytes = std::fs::read(self.bytes_path(key)).ok()?;
+        let mime = std::fs::read_to_string(self.mime_path(key))
+            .unwrap_or_else(|_| "application/octet-stream".to_string());
+        Some((bytes, mime))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// Downloads `url` with `client`, caches the bytes under a content-addressed
+/// key, and returns the local `ogimg://` URI the webview should use instead.
+pub async fn cache_remote_image(
+    client: &reqwest::Client,
+    cache: &ImageCache,
+    url: &str,
+) -> Result<String, String> {
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let mime = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+
+    let key = ImageCache::key_for(url, &bytes);
+    cache.store(&key, &bytes, &mime).map_err(|e| e.to_string())?;
+
+    Ok(format!("ogimg://localhost/{key}"))
+}
@@ -1,4 +1,11 @@
 mod commands;
+mod error;
+mod image_cache;
+mod metadata_cache;
+
+use image_cache::ImageCache;
+use metadata_cache::MetadataCache;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -9,7 +16,35 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .register_uri_scheme_protocol("ogimg", |ctx, request| {
+            let key = request.uri().path().trim_start_matches('/');
+            let cache = ctx.app_handle().state::<ImageCache>();
+
+            match cache.load(key) {
+                Some((bytes, mime)) => tauri::http::Response::builder()
+                    .status(tauri::http::StatusCode::OK)
+                    .header(tauri::http::header::CONTENT_TYPE, mime)
+                    .body(bytes)
+                    .unwrap(),
+                None => tauri::http::Response::builder()
+                    .status(tauri::http::StatusCode::NOT_FOUND)
+                    .body(Vec::new())
+                    .unwrap(),
+            }
+        })
         .setup(|app| {
+            let cache_dir = app.path().app_cache_dir()?.join("link-images");
+            app.manage(ImageCache::new(cache_dir)?);
+
+            let app_data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&app_data_dir)?;
+            let metadata_db_path = app_data_dir.join("link_metadata_cache.db");
+            let metadata_cache = tauri::async_runtime::block_on(MetadataCache::new(
+                &metadata_db_path,
+                metadata_cache::DEFAULT_TTL,
+            ))?;
+            app.manage(metadata_cache);
+
             if commands::is_dev() {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
@@ -22,7 +57,12 @@ pub fn run() {
             }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![commands::is_dev, commands::fetch_link_metadata])
+        .invoke_handler(tauri::generate_handler![
+            commands::is_dev,
+            commands::fetch_link_metadata,
+            commands::fetch_link_metadata_batch,
+            commands::invalidate_link_metadata,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use tokio::sync::Mutex;
+
+use crate::commands::LinkMetadata;
+
+/// Default TTL (7 days) for cached link metadata. `MetadataCache::new` has no
+/// fallback of its own: it always takes an explicit `ttl`, and callers pass
+/// this constant to get the default.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+struct CachedEntry {
+    metadata: LinkMetadata,
+    fetched_at: u64,
+}
+
+impl CachedEntry {
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        now_unix().saturating_sub(self.fetched_at) < ttl.as_secs()
+    }
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// In-memory, TTL'd cache of resolved link previews, mirrored to its own
+/// SQLite database (opened with `sqlx`, not through `tauri_plugin_sql`) so
+/// entries survive app restarts.
+pub struct MetadataCache {
+    entries: Mutex<HashMap<String, CachedEntry>>,
+    pool: SqlitePool,
+    ttl: Duration,
+}
+
+impl MetadataCache {
+    /// Opens (and migrates, if needed) the cache database at `db_path`.
+    ///
+    /// This connects directly via `sqlx` rather than going through
+    /// `tauri_plugin_sql`: the plugin's connection pool is private state
+    /// it manages for its own JS-facing commands and isn't exposed for
+    /// Rust-side reuse, so backend code needs its own pool regardless.
+    pub async fn new(db_path: &Path, ttl: Duration) -> Result<Self, sqlx::Error> {
+        let pool = SqlitePoolOptions::new()
+            .connect(&format!("sqlite://{}?mode=rwc", db_path.display()))
+            .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS link_metadata_cache (
+                url TEXT PRIMARY KEY,
+                metadata TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        let rows: Vec<(String, String, i64)> =
+            sqlx::query_as("SELECT url, metadata, fetched_at FROM link_metadata_cache")
+                .fetch_all(&pool)
+                .await?;
+
+        let mut entries = HashMap::with_capacity(rows.len());
+        for (url, metadata, fetched_at) in rows {
+            if let Ok(metadata) = serde_json::from_str(&metadata) {
+                entries.insert(
+                    url,
+                    CachedEntry {
+                        metadata,
+                        fetched_at: fetched_at as u64,
+                    },
+                );
+            }
+        }
+
+        Ok(Self {
+            entries: Mutex::new(entries),
+            pool,
+            ttl,
+        })
+    }
+
+    /// Returns the cached metadata for `url` if present and still within
+    /// the TTL, without touching the network.
+    pub async fn get_fresh(&self, url: &str) -> Option<LinkMetadata> {
+        let entries = self.entries.lock().await;
+        entries
+            .get(url)
+            .filter(|entry| entry.is_fresh(self.ttl))
+            .map(|entry| entry.metadata.clone())
+    }
+
+    /// Records a freshly-fetched result, both in memory and in the backing
+    /// database.
+    pub async fn put(&self, url: &str, metadata: LinkMetadata) {
+        let fetched_at = now_unix();
+
+        if let Ok(json) = serde_json::to_string(&metadata) {
+            let _ = sqlx::query(
+                "INSERT INTO link_metadata_cache (url, metadata, fetched_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(url) DO UPDATE SET metadata = excluded.metadata, fetched_at = excluded.fetched_at",
+            )
+            .bind(url)
+            .bind(json)
+            .bind(fetched_at as i64)
+            .execute(&self.pool)
+            .await;
+        }
+
+        self.entries
+            .lock()
+            .await
+            .insert(url.to_string(), CachedEntry { metadata, fetched_at });
+    }
+
+    /// Evicts `url` from both the in-memory map and the backing database.
+    pub async fn invalidate(&self, url: &str) {
+        self.entries.lock().await.remove(url);
+        let _ = sqlx::query("DELETE FROM link_metadata_cache WHERE url = ?1")
+            .bind(url)
+            .execute(&self.pool)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static TEST_DB_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    fn sample_metadata(url: &str) -> LinkMetadata {
+        serde_json::from_str(&format!(
+            r#"{{"title":"Example","description":null,"image":null,"site_name":null,"favicon":null,"url":"{url}"}}"#
+        ))
+        .unwrap()
+    }
+
+    async fn temp_cache(ttl: Duration) -> MetadataCache {
+        let id = TEST_DB_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "second-brain-metadata-cache-test-{}-{id}.db",
+            std::process::id()
+        ));
+        MetadataCache::new(&path, ttl).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_fresh_is_keyed_by_the_put_key_not_by_metadata_url() {
+        let cache = temp_cache(DEFAULT_TTL).await;
+        // Simulate a redirecting link: callers look up by the URL they asked
+        // for, even though the fetched metadata's own `url` is the final one.
+        cache
+            .put(
+                "https://short.link/abc",
+                sample_metadata("https://example.com/real-article"),
+            )
+            .await;
+
+        assert!(cache.get_fresh("https://short.link/abc").await.is_some());
+        assert!(cache
+            .get_fresh("https://example.com/real-article")
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn get_fresh_misses_once_the_ttl_has_elapsed() {
+        let cache = temp_cache(Duration::from_secs(0)).await;
+        cache
+            .put("https://example.com", sample_metadata("https://example.com"))
+            .await;
+
+        assert!(cache.get_fresh("https://example.com").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn invalidate_removes_the_entry() {
+        let cache = temp_cache(DEFAULT_TTL).await;
+        cache
+            .put("https://example.com", sample_metadata("https://example.com"))
+            .await;
+        assert!(cache.get_fresh("https://example.com").await.is_some());
+
+        cache.invalidate("https://example.com").await;
+        assert!(cache.get_fresh("https://example.com").await.is_none());
+    }
+}